@@ -1,13 +1,19 @@
+use actix_multipart::Multipart;
 use actix_web::{
     middleware::Logger,
     web, App, HttpResponse, HttpServer,
     Result as ActixResult,
 };
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use futures_util::StreamExt as _;
 use image::{Rgba, RgbaImage, imageops::FilterType, ImageBuffer};
+use num_bigint::BigUint;
 use qrc::{QRCode, qr_code_to};
 use reqwest;
-use std::io::Cursor;
-use serde::Deserialize;
+use std::io::{Cursor, Read, Write};
+use serde::{Deserialize, Serialize};
 use moka::future::Cache;
 use std::time::Duration;
 
@@ -18,11 +24,114 @@ struct QRCacheKey {
     size: u32,
     fg_color: Option<String>,
     bg_color: Option<String>,
+    ec_level: ECLevel,
+    format: OutputFormat,
+    compress: bool,
+    url_template: Option<String>,
+    render: Option<RenderMode>,
+    quiet_zone: bool,
+    dark_char: char,
+    light_char: char,
+    logo_url: Option<String>,
+}
+
+/// A cached render: the bytes plus whether the requested logo (if any)
+/// actually got applied, so a cache hit can replay `X-Logo-Warning`
+/// instead of silently dropping it.
+#[derive(Clone)]
+struct CachedQr {
+    buffer: Vec<u8>,
+    logo_warning: Option<String>,
 }
 
 // Shared state structure
 struct AppState {
-    cache: Cache<QRCacheKey, Vec<u8>>,
+    cache: Cache<QRCacheKey, CachedQr>,
+    logo_cache: Cache<LogoCacheKey, RgbaImage>,
+    failed_logo_cache: Cache<LogoCacheKey, ()>,
+}
+
+/// Key for the decoded/resized logo cache and its negative-cache sibling.
+#[derive(Hash, Eq, PartialEq, Clone)]
+struct LogoCacheKey {
+    logo_url: String,
+    size: u32,
+}
+
+#[derive(Deserialize, Hash, Eq, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+enum ECLevel {
+    L,
+    M,
+    Q,
+    H,
+}
+
+impl ECLevel {
+    /// Fraction of modules that can be occluded (e.g. by a logo) while the
+    /// code remains decodable at this error-correction level.
+    fn max_occlusion_ratio(&self) -> f32 {
+        match self {
+            ECLevel::L => 0.07,
+            ECLevel::M => 0.15,
+            ECLevel::Q => 0.25,
+            ECLevel::H => 0.30,
+        }
+    }
+
+    /// Largest numeric-mode payload (in decimal digits) a version-40 QR
+    /// code can hold at this error-correction level.
+    fn max_numeric_digits(&self) -> usize {
+        match self {
+            ECLevel::L => 7089,
+            ECLevel::M => 5596,
+            ECLevel::Q => 3993,
+            ECLevel::H => 3057,
+        }
+    }
+
+    /// Largest byte-mode payload (in bytes) a version-40 QR code can hold
+    /// at this error-correction level. Used when `url_template` mixes the
+    /// numeric payload with URL text, since the combined content can no
+    /// longer be encoded as a pure numeric segment.
+    fn max_byte_capacity(&self) -> usize {
+        match self {
+            ECLevel::L => 2953,
+            ECLevel::M => 2331,
+            ECLevel::Q => 1663,
+            ECLevel::H => 1273,
+        }
+    }
+}
+
+#[derive(Deserialize, Hash, Eq, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    Png,
+    Svg,
+    Jpeg,
+    Webp,
+}
+
+impl OutputFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Svg => "image/svg+xml",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Webp => "image/webp",
+        }
+    }
+}
+
+/// Terminal/text rendering mode for `render=ascii|unicode`.
+#[derive(Deserialize, Hash, Eq, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+enum RenderMode {
+    /// Two characters per module horizontally, one text row per module row.
+    Ascii,
+    /// Half-block glyphs compress two module rows into one text row.
+    Unicode,
 }
 
 #[derive(Deserialize)]
@@ -32,19 +141,90 @@ struct QRParams {
     fg_color: Option<String>,
     bg_color: Option<String>,
     logo_url: Option<String>,
+    ec_level: Option<ECLevel>,
+    format: Option<OutputFormat>,
+    compress: Option<bool>,
+    url_template: Option<String>,
+    render: Option<RenderMode>,
+    quiet_zone: Option<bool>,
+    dark_char: Option<char>,
+    light_char: Option<char>,
+}
+
+impl QRParams {
+    /// Defaults to `H` whenever a logo is present, since overlaying a logo
+    /// occludes modules and needs the extra redundancy to stay scannable.
+    fn resolved_ec_level(&self) -> ECLevel {
+        self.ec_level.unwrap_or(if self.logo_url.is_some() {
+            ECLevel::H
+        } else {
+            ECLevel::M
+        })
+    }
+
+    fn resolved_quiet_zone(&self) -> bool {
+        self.quiet_zone.unwrap_or(true)
+    }
+
+    fn resolved_dark_char(&self) -> char {
+        self.dark_char.unwrap_or('#')
+    }
+
+    fn resolved_light_char(&self) -> char {
+        self.light_char.unwrap_or(' ')
+    }
+
+    fn resolved_format(&self) -> OutputFormat {
+        self.format.unwrap_or(OutputFormat::Png)
+    }
 }
 
-async fn fetch_and_resize_logo(url: &str, size: u32) -> Result<RgbaImage, Box<dyn std::error::Error>> {
+async fn fetch_and_resize_logo(url: &str, logo_size: u32) -> Result<RgbaImage, Box<dyn std::error::Error>> {
     let response = reqwest::get(url).await?;
     let bytes = response.bytes().await?;
     let img = image::load_from_memory(&bytes)?;
 
-    let logo_size = size / 4;
     let resized = img.resize(logo_size, logo_size, FilterType::Lanczos3);
 
     Ok(resized.to_rgba8())
 }
 
+/// Fetches and resizes a logo through the decoded-image cache, short-circuiting
+/// on a cached negative entry instead of re-hitting a flaky logo host on every
+/// request. `logo_size` should be the resolved EC-level-aware safe-zone size
+/// so the source logo is decoded at (or near) its actual target resolution,
+/// rather than at a fixed fraction of `size` that then needs a second,
+/// blurrier resize in the caller.
+async fn fetch_and_resize_logo_cached(
+    app_state: &AppState,
+    url: &str,
+    logo_size: u32,
+) -> Result<RgbaImage, Box<dyn std::error::Error>> {
+    let key = LogoCacheKey {
+        logo_url: url.to_string(),
+        size: logo_size,
+    };
+
+    if app_state.failed_logo_cache.get(&key).await.is_some() {
+        return Err(format!("logo fetch for {url} failed recently (cached)").into());
+    }
+
+    if let Some(logo) = app_state.logo_cache.get(&key).await {
+        return Ok(logo);
+    }
+
+    match fetch_and_resize_logo(url, logo_size).await {
+        Ok(logo) => {
+            app_state.logo_cache.insert(key, logo.clone()).await;
+            Ok(logo)
+        }
+        Err(e) => {
+            app_state.failed_logo_cache.insert(key, ()).await;
+            Err(e)
+        }
+    }
+}
+
 fn hex_to_rgba(hex: &str) -> Result<Rgba<u8>, String> {
     if hex.len() != 7 || !hex.starts_with('#') {
         return Err("Invalid hex color format".to_string());
@@ -57,19 +237,297 @@ fn hex_to_rgba(hex: &str) -> Result<Rgba<u8>, String> {
     Ok(Rgba([r, g, b, 255]))
 }
 
-fn calculate_safe_zone(qr_size: u32) -> (u32, u32, u32, u32) {
-    // Calculate the center zone that's safe for logo placement
-    // Typically, QR codes can have up to 30% error correction
-    let safe_size = qr_size / 4;  // 25% of QR size
+/// Calculates the center zone that's safe for logo placement: the largest
+/// square whose area stays within the occlusion budget the chosen
+/// error-correction level can recover from, applied to the actual *data
+/// module* count rather than the full rendered canvas. The quiet zone is a
+/// fixed 4-module border regardless of version, so computing the ratio
+/// against raw pixels overestimates the safe footprint for low-version
+/// (short content) codes, where the quiet zone is a much larger share of
+/// the canvas. `modules` is the data-module count from `QRCode::size()`,
+/// matching the `quiet_zone * 2` convention used by `render_qr_svg`/`render_qr_text`.
+fn calculate_safe_zone(qr_size: u32, ec_level: ECLevel, modules: u32) -> (u32, u32, u32, u32) {
+    let quiet_zone_modules = 4u32;
+    let total_modules = modules + quiet_zone_modules * 2;
+    let module_size_px = qr_size as f32 / total_modules as f32;
+
+    let occlusion_ratio = ec_level.max_occlusion_ratio();
+    let safe_modules = modules as f32 * occlusion_ratio.sqrt();
+    let safe_size = (safe_modules * module_size_px) as u32;
+
     let start_x = (qr_size - safe_size) / 2;
     let start_y = (qr_size - safe_size) / 2;
 
     (start_x, start_y, safe_size, safe_size)
 }
 
-async fn generate_qr_image(params: &QRParams, size: u32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    // Generate QR code with size
-    let png = qr_code_to!(params.content.clone().into(), "png", size);
+/// A compressed payload (bare digits, or digits wrapped in `url_template`)
+/// would not fit in the largest QR version at the requested
+/// error-correction level.
+#[derive(Debug)]
+struct CapacityExceededError(String);
+
+impl std::fmt::Display for CapacityExceededError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for CapacityExceededError {}
+
+/// Zlib-compresses `content` and renders the compressed bytes as a
+/// base-10 numeric string, suitable for QR numeric-mode encoding (which
+/// packs 3 digits per 10 bits, denser than byte mode for this data).
+///
+/// A `0xFF` sentinel is prepended before the big-integer conversion so
+/// leading zero bytes in the compressed stream survive the round trip;
+/// `BigUint` otherwise drops them.
+fn encode_numeric_payload(content: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(content.as_bytes())?;
+    let compressed = encoder.finish()?;
+
+    let mut framed = vec![0xFFu8];
+    framed.extend_from_slice(&compressed);
+
+    Ok(BigUint::from_bytes_be(&framed).to_str_radix(10))
+}
+
+/// Reverses [`encode_numeric_payload`]: parses a decimal digit string
+/// back into bytes, strips the sentinel, and zlib-inflates the rest.
+fn decode_numeric_payload(digits: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let value = BigUint::parse_bytes(digits.as_bytes(), 10).ok_or("Invalid numeric payload")?;
+    let mut framed = value.to_bytes_be();
+    if framed.is_empty() || framed.remove(0) != 0xFF {
+        return Err("Malformed numeric payload framing".into());
+    }
+
+    let mut decompressed = String::new();
+    ZlibDecoder::new(&framed[..]).read_to_string(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Appends the numeric payload as a `d` query parameter on the
+/// caller-supplied base URL.
+fn apply_url_template(url_template: &str, digits: &str) -> String {
+    let separator = if url_template.contains('?') { '&' } else { '?' };
+    format!("{url_template}{separator}d={digits}")
+}
+
+/// Extracts the `d` query parameter from a `url_template`-wrapped payload,
+/// or returns `content` unchanged if it is a bare digit string. Matches
+/// `d` as a whole query-parameter name (anchored on `?`/`&` boundaries),
+/// not a bare substring, so a template like `...?uid=42&d=12345` can't
+/// have its `d=` match swallow the `uid=42` parameter instead.
+fn extract_numeric_digits(content: &str) -> &str {
+    let query = match content.find('?') {
+        Some(idx) => &content[idx + 1..],
+        None => content,
+    };
+
+    for pair in query.split('&') {
+        if let Some(value) = pair.strip_prefix("d=") {
+            return value;
+        }
+    }
+
+    content
+}
+
+/// Maps our public `ECLevel` onto the `qrc` crate's error-correction
+/// selector, which expects the familiar L/M/Q/H letter.
+fn ec_level_code(ec_level: ECLevel) -> &'static str {
+    match ec_level {
+        ECLevel::L => "L",
+        ECLevel::M => "M",
+        ECLevel::Q => "Q",
+        ECLevel::H => "H",
+    }
+}
+
+/// Renders the QR code as an SVG document: one `<rect>` per dark module
+/// plus the surrounding quiet zone, rather than rasterized pixels. This
+/// keeps the output resolution-independent and far smaller than a PNG.
+fn render_qr_svg(
+    content: &str,
+    ec_level: ECLevel,
+    size: u32,
+    fg_color: &Option<String>,
+    bg_color: &Option<String>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let code = QRCode::new(content.as_bytes(), ec_level_code(ec_level))?;
+    let modules = code.size() as u32;
+    let quiet_zone = 4u32;
+    let total_modules = modules + quiet_zone * 2;
+    let module_size = size as f32 / total_modules as f32;
+
+    let bg = bg_color.as_ref().map_or("#ffffff", |c| c.as_str());
+    let dark = fg_color.as_ref().map_or("#000000", |c| c.as_str());
+
+    let mut svg = String::new();
+    svg.push_str(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{size}\" viewBox=\"0 0 {size} {size}\">\n"
+    ));
+    svg.push_str(&format!("<rect width=\"100%\" height=\"100%\" fill=\"{bg}\"/>\n"));
+
+    for y in 0..modules {
+        for x in 0..modules {
+            if code.get_module(x as i32, y as i32) {
+                let px = (x + quiet_zone) as f32 * module_size;
+                let py = (y + quiet_zone) as f32 * module_size;
+                svg.push_str(&format!(
+                    "<rect x=\"{px:.3}\" y=\"{py:.3}\" width=\"{module_size:.3}\" height=\"{module_size:.3}\" fill=\"{dark}\"/>\n"
+                ));
+            }
+        }
+    }
+    svg.push_str("</svg>\n");
+
+    Ok(svg.into_bytes())
+}
+
+/// Renders the QR code as a text grid for terminal/log consumers: ASCII
+/// mode prints two characters per module, Unicode mode compresses two
+/// module rows into one text row using half-block glyphs.
+fn render_qr_text(
+    content: &str,
+    ec_level: ECLevel,
+    render_mode: RenderMode,
+    quiet_zone: bool,
+    dark_char: char,
+    light_char: char,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let code = QRCode::new(content.as_bytes(), ec_level_code(ec_level))?;
+    let modules = code.size();
+    let margin: i32 = if quiet_zone { 4 } else { 0 };
+    let total = modules + margin * 2;
+
+    let is_dark = |x: i32, y: i32| -> bool {
+        if x < margin || y < margin || x >= margin + modules || y >= margin + modules {
+            false
+        } else {
+            code.get_module(x - margin, y - margin)
+        }
+    };
+
+    let mut out = String::new();
+    match render_mode {
+        RenderMode::Ascii => {
+            for y in 0..total {
+                for x in 0..total {
+                    let ch = if is_dark(x, y) { dark_char } else { light_char };
+                    out.push(ch);
+                    out.push(ch);
+                }
+                out.push('\n');
+            }
+        }
+        RenderMode::Unicode => {
+            let mut y = 0;
+            while y < total {
+                for x in 0..total {
+                    let top = is_dark(x, y);
+                    let bottom = y + 1 < total && is_dark(x, y + 1);
+                    let glyph = match (top, bottom) {
+                        (true, true) => '\u{2588}',  // █
+                        (true, false) => '\u{2580}', // ▀
+                        (false, true) => '\u{2584}', // ▄
+                        (false, false) => ' ',
+                    };
+                    out.push(glyph);
+                }
+                out.push('\n');
+                y += 2;
+            }
+        }
+    }
+
+    Ok(out.into_bytes())
+}
+
+/// Resolves the literal string that actually gets encoded into the QR
+/// code, applying `compress`/`url_template` if requested.
+fn resolve_encoded_content(params: &QRParams, ec_level: ECLevel) -> Result<String, Box<dyn std::error::Error>> {
+    if !params.compress.unwrap_or(false) {
+        return Ok(params.content.clone());
+    }
+
+    let digits = encode_numeric_payload(&params.content)?;
+
+    match &params.url_template {
+        // The URL text and `?`/`&d=` wrapper around the digits ride in the
+        // same version-40 byte-mode budget as the digits themselves, so
+        // check the full assembled content against the byte-mode ceiling
+        // rather than the bare digit count against the numeric-mode one.
+        Some(template) => {
+            let final_content = apply_url_template(template, &digits);
+            let max_bytes = ec_level.max_byte_capacity();
+            if final_content.len() > max_bytes {
+                return Err(Box::new(CapacityExceededError(format!(
+                    "Templated payload needs {} bytes, which exceeds the {} byte ceiling \
+                     for EC level {:?} at the largest QR version (40)",
+                    final_content.len(),
+                    max_bytes,
+                    ec_level
+                ))));
+            }
+            Ok(final_content)
+        }
+        None => {
+            let max_digits = ec_level.max_numeric_digits();
+            if digits.len() > max_digits {
+                return Err(Box::new(CapacityExceededError(format!(
+                    "Compressed payload needs {} numeric digits, which exceeds the {} digit ceiling \
+                     for EC level {:?} at the largest QR version (40)",
+                    digits.len(),
+                    max_digits,
+                    ec_level
+                ))));
+            }
+            Ok(digits)
+        }
+    }
+}
+
+/// Result of [`generate_qr_image`]: the rendered bytes, plus an optional
+/// note when a requested logo could not be applied.
+struct GeneratedQr {
+    buffer: Vec<u8>,
+    logo_warning: Option<String>,
+}
+
+async fn generate_qr_image(
+    params: &QRParams,
+    size: u32,
+    app_state: &AppState,
+) -> Result<GeneratedQr, Box<dyn std::error::Error>> {
+    let ec_level = params.resolved_ec_level();
+    let content = resolve_encoded_content(params, ec_level)?;
+
+    if let Some(render_mode) = params.render {
+        let buffer = render_qr_text(
+            &content,
+            ec_level,
+            render_mode,
+            params.resolved_quiet_zone(),
+            params.resolved_dark_char(),
+            params.resolved_light_char(),
+        )?;
+        return Ok(GeneratedQr { buffer, logo_warning: None });
+    }
+
+    if let OutputFormat::Svg = params.resolved_format() {
+        let buffer = render_qr_svg(&content, ec_level, size, &params.fg_color, &params.bg_color)?;
+        return Ok(GeneratedQr { buffer, logo_warning: None });
+    }
+
+    // Generate QR code with size at the requested error-correction level
+    let png = qr_code_to!(
+        content.clone().into(),
+        "png",
+        size,
+        ec_level_code(ec_level)
+    );
     let png_data = png.into_raw();
 
     // Create image buffer from raw data
@@ -94,54 +552,90 @@ async fn generate_qr_image(params: &QRParams, size: u32) -> Result<Vec<u8>, Box<
     }
 
     // Add logo if provided
+    let mut logo_warning = None;
     if let Some(logo_url) = &params.logo_url {
-        if let Ok(mut logo) = fetch_and_resize_logo(logo_url, size).await {
-            // Calculate safe zone for logo
-            let (start_x, start_y, safe_width, safe_height) = calculate_safe_zone(size);
-
-            // Resize logo to fit in safe zone
-            logo = image::imageops::resize(&logo,
-                safe_width,
-                safe_height,
-                FilterType::Lanczos3);
-
-            // Create white background for logo
-            let margin = 4; // pixels of white margin around logo
-            for y in start_y.saturating_sub(margin)..start_y + safe_height + margin {
-                for x in start_x.saturating_sub(margin)..start_x + safe_width + margin {
-                    if x < size && y < size {
-                        image.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+        // Calculate safe zone for logo, clamped to the redundancy budget
+        // of the chosen error-correction level so the logo can never push
+        // the code past what it can recover from. Source the logo at this
+        // resolution directly, instead of fetching at a fixed fraction of
+        // `size` and upscaling afterwards.
+        let modules = QRCode::new(content.as_bytes(), ec_level_code(ec_level))?.size() as u32;
+        let (zone_x, zone_y, zone_width, zone_height) = calculate_safe_zone(size, ec_level, modules);
+
+        // The white margin around the logo is drawn inside the safe zone, so
+        // margin plus logo together never occlude more than the zone itself.
+        let margin = 4u32.min(zone_width / 2); // pixels of white margin around logo
+        let (start_x, start_y) = (zone_x + margin, zone_y + margin);
+        let (safe_width, safe_height) = (zone_width - 2 * margin, zone_height - 2 * margin);
+
+        match fetch_and_resize_logo_cached(app_state, logo_url, safe_width).await {
+            Err(e) => logo_warning = Some(format!("logo could not be applied: {e}")),
+            Ok(mut logo) => {
+                // The cached logo fits within a `safe_width` square, but the
+                // fetch keeps the source aspect ratio, so a non-square logo
+                // comes back short on one side; stretch it to the full zone.
+                if logo.width() != safe_width || logo.height() != safe_height {
+                    logo = image::imageops::resize(&logo, safe_width, safe_height, FilterType::Lanczos3);
+                }
+
+                // Create white background for logo
+                for y in start_y.saturating_sub(margin)..start_y + safe_height + margin {
+                    for x in start_x.saturating_sub(margin)..start_x + safe_width + margin {
+                        if x < size && y < size {
+                            image.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+                        }
                     }
                 }
-            }
 
-            // Overlay logo with transparency handling
-            for (x, y, pixel) in logo.enumerate_pixels() {
-                let target_x = start_x + x;
-                let target_y = start_y + y;
-                if target_x < size && target_y < size {
-                    // Alpha blending
-                    if pixel[3] > 0 {
-                        let alpha = pixel[3] as f32 / 255.0;
-                        let existing = image.get_pixel(target_x, target_y);
-                        let blended = Rgba([
-                            ((1.0 - alpha) * existing[0] as f32 + alpha * pixel[0] as f32) as u8,
-                            ((1.0 - alpha) * existing[1] as f32 + alpha * pixel[1] as f32) as u8,
-                            ((1.0 - alpha) * existing[2] as f32 + alpha * pixel[2] as f32) as u8,
-                            255,
-                        ]);
-                        image.put_pixel(target_x, target_y, blended);
+                // Overlay logo with transparency handling
+                for (x, y, pixel) in logo.enumerate_pixels() {
+                    let target_x = start_x + x;
+                    let target_y = start_y + y;
+                    if target_x < size && target_y < size {
+                        // Alpha blending
+                        if pixel[3] > 0 {
+                            let alpha = pixel[3] as f32 / 255.0;
+                            let existing = image.get_pixel(target_x, target_y);
+                            let blended = Rgba([
+                                ((1.0 - alpha) * existing[0] as f32 + alpha * pixel[0] as f32) as u8,
+                                ((1.0 - alpha) * existing[1] as f32 + alpha * pixel[1] as f32) as u8,
+                                ((1.0 - alpha) * existing[2] as f32 + alpha * pixel[2] as f32) as u8,
+                                255,
+                            ]);
+                            image.put_pixel(target_x, target_y, blended);
+                        }
                     }
                 }
             }
         }
     }
 
-    // Convert to binary
+    // Convert to binary in the requested raster format. Requires the
+    // `image` crate to be built with its `jpeg` and `webp` encoder
+    // features enabled.
+    let raster_format = params.resolved_format();
+    let output_format = match raster_format {
+        OutputFormat::Jpeg => image::ImageOutputFormat::Jpeg(90),
+        OutputFormat::Webp => image::ImageOutputFormat::WebP,
+        OutputFormat::Png | OutputFormat::Svg => image::ImageOutputFormat::Png,
+    };
+
     let mut buffer = Vec::new();
-    image.write_to(&mut Cursor::new(&mut buffer), image::ImageOutputFormat::Png)?;
+    match raster_format {
+        // JPEG has no alpha channel, and the `image` crate's JPEG encoder
+        // only accepts an RGB8 source buffer, not RGBA8 — drop the alpha
+        // channel before encoding or every `format=jpeg` request 500s.
+        OutputFormat::Jpeg => {
+            image::DynamicImage::ImageRgba8(image)
+                .to_rgb8()
+                .write_to(&mut Cursor::new(&mut buffer), output_format)?;
+        }
+        OutputFormat::Png | OutputFormat::Svg | OutputFormat::Webp => {
+            image.write_to(&mut Cursor::new(&mut buffer), output_format)?;
+        }
+    }
 
-    Ok(buffer)
+    Ok(GeneratedQr { buffer, logo_warning })
 }
 
 async fn generate_qr(
@@ -149,6 +643,8 @@ async fn generate_qr(
     data: web::Data<AppState>,
 ) -> ActixResult<HttpResponse> {
     let size = params.size.unwrap_or(512);
+    let format = params.resolved_format();
+    let content_type = params.render.map_or(format.content_type(), |_| "text/plain");
 
     // Create cache key
     let cache_key = QRCacheKey {
@@ -156,26 +652,128 @@ async fn generate_qr(
         size,
         fg_color: params.fg_color.clone(),
         bg_color: params.bg_color.clone(),
+        ec_level: params.resolved_ec_level(),
+        format,
+        compress: params.compress.unwrap_or(false),
+        url_template: params.url_template.clone(),
+        render: params.render,
+        quiet_zone: params.resolved_quiet_zone(),
+        dark_char: params.resolved_dark_char(),
+        light_char: params.resolved_light_char(),
+        logo_url: params.logo_url.clone(),
     };
 
     // Try to get from cache
-    if let Some(cached_data) = data.cache.get(&cache_key).await {
-        return Ok(HttpResponse::Ok()
-            .content_type("image/png")
-            .body(cached_data));
+    if let Some(cached) = data.cache.get(&cache_key).await {
+        let mut response = HttpResponse::Ok();
+        response.content_type(content_type);
+        if let Some(warning) = &cached.logo_warning {
+            response.insert_header(("X-Logo-Warning", warning.as_str()));
+        }
+        return Ok(response.body(cached.buffer));
     }
 
     // Generate new QR code if not in cache
-    let buffer = generate_qr_image(&params, size).await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+    let generated = generate_qr_image(&params, size, &data).await.map_err(|e| {
+        if e.downcast_ref::<CapacityExceededError>().is_some() {
+            actix_web::error::ErrorPayloadTooLarge(e.to_string())
+        } else {
+            actix_web::error::ErrorInternalServerError(e)
+        }
+    })?;
 
     // Store in cache
-    data.cache.insert(cache_key, buffer.clone()).await;
+    let cached = CachedQr {
+        buffer: generated.buffer,
+        logo_warning: generated.logo_warning,
+    };
+    data.cache.insert(cache_key, cached.clone()).await;
+
+    // Return response, surfacing a logo-fetch failure instead of silently
+    // succeeding without the logo
+    let mut response = HttpResponse::Ok();
+    response.content_type(content_type);
+    if let Some(warning) = &cached.logo_warning {
+        response.insert_header(("X-Logo-Warning", warning.as_str()));
+    }
+    Ok(response.body(cached.buffer))
+}
 
-    // Return response
-    Ok(HttpResponse::Ok()
-        .content_type("image/png")
-        .body(buffer))
+#[derive(Deserialize)]
+struct DecodeQRParams {
+    image_url: Option<String>,
+    decompress: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct DecodedQR {
+    content: String,
+    version: i16,
+    ec_level: String,
+}
+
+/// Reads the raw bytes of the first non-empty field in a multipart upload.
+async fn read_multipart_image(mut payload: Multipart) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    while let Some(field) = payload.next().await {
+        let mut field = field?;
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.next().await {
+            bytes.extend_from_slice(&chunk?);
+        }
+        if !bytes.is_empty() {
+            return Ok(bytes);
+        }
+    }
+
+    Err("No image field found in multipart upload".into())
+}
+
+fn decode_qr_from_bytes(bytes: &[u8]) -> Result<DecodedQR, Box<dyn std::error::Error>> {
+    let img = image::load_from_memory(bytes)?;
+    let luma = img.to_luma8();
+
+    let mut prepared = rqrr::PreparedImage::prepare(luma);
+    let grids = prepared.detect_grids();
+    let grid = grids.into_iter().next().ok_or("No QR code detected in image")?;
+
+    let (meta, content) = grid.decode()?;
+
+    Ok(DecodedQR {
+        content,
+        version: meta.version.0,
+        ec_level: format!("{:?}", meta.ecc_level),
+    })
+}
+
+async fn decode_qr(
+    mut payload: Option<Multipart>,
+    query: web::Query<DecodeQRParams>,
+) -> ActixResult<HttpResponse> {
+    let bytes = if let Some(multipart) = payload.take() {
+        read_multipart_image(multipart).await
+            .map_err(actix_web::error::ErrorBadRequest)?
+    } else if let Some(image_url) = &query.image_url {
+        let response = reqwest::get(image_url).await
+            .map_err(actix_web::error::ErrorBadRequest)?;
+        response.bytes().await
+            .map_err(actix_web::error::ErrorBadRequest)?
+            .to_vec()
+    } else {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Provide either a multipart image upload or an image_url query parameter",
+        ));
+    };
+
+    let mut decoded = decode_qr_from_bytes(&bytes)
+        .map_err(actix_web::error::ErrorUnprocessableEntity)?;
+
+    if query.decompress.unwrap_or(false) {
+        let digits = extract_numeric_digits(&decoded.content);
+        decoded.content = decode_numeric_payload(digits)
+            .map_err(actix_web::error::ErrorUnprocessableEntity)?;
+    }
+
+    Ok(HttpResponse::Ok().json(decoded))
 }
 
 #[actix_web::main]
@@ -183,13 +781,30 @@ async fn main() -> std::io::Result<()> {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
     // Initialize cache
-    let cache: Cache<QRCacheKey, Vec<u8>> = Cache::builder()
+    let cache: Cache<QRCacheKey, CachedQr> = Cache::builder()
         .time_to_live(Duration::from_secs(3600)) // Cache for 1 hour
         .time_to_idle(Duration::from_secs(1800)) // Remove if not accessed for 30 minutes
         .max_capacity(1000) // Maximum number of items in cache
         .build();
 
-    let app_state = web::Data::new(AppState { cache });
+    // Decoded/resized logo cache, keyed by (logo_url, size)
+    let logo_cache: Cache<LogoCacheKey, RgbaImage> = Cache::builder()
+        .time_to_live(Duration::from_secs(3600)) // Cache for 1 hour
+        .max_capacity(500)
+        .build();
+
+    // Negative cache so a flaky logo host doesn't get hammered on every
+    // request; short TTL so a fixed logo is picked up again reasonably fast
+    let failed_logo_cache: Cache<LogoCacheKey, ()> = Cache::builder()
+        .time_to_live(Duration::from_secs(60))
+        .max_capacity(500)
+        .build();
+
+    let app_state = web::Data::new(AppState {
+        cache,
+        logo_cache,
+        failed_logo_cache,
+    });
 
     println!("Server starting at http://127.0.0.1:8080");
 
@@ -198,6 +813,8 @@ async fn main() -> std::io::Result<()> {
             .app_data(app_state.clone())
             .wrap(Logger::default())
             .route("/generate-qr", web::get().to(generate_qr))
+            .route("/decode-qr", web::get().to(decode_qr))
+            .route("/decode-qr", web::post().to(decode_qr))
             .route("/health", web::get().to(health_check))
     })
     .bind(("0.0.0.0", 8080))?
@@ -208,3 +825,81 @@ async fn main() -> std::io::Result<()> {
 async fn health_check() -> ActixResult<HttpResponse> {
     Ok(HttpResponse::Ok().json(serde_json::json!({"status": "healthy"})))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jpeg_encode_drops_alpha_channel_without_erroring() {
+        // The `image` crate's JPEG encoder rejects RGBA8 buffers directly;
+        // converting to RGB8 first must succeed for every `format=jpeg`
+        // request instead of 500ing.
+        let img = RgbaImage::from_pixel(8, 8, Rgba([10, 20, 30, 128]));
+        let mut buffer = Vec::new();
+        image::DynamicImage::ImageRgba8(img)
+            .to_rgb8()
+            .write_to(&mut Cursor::new(&mut buffer), image::ImageOutputFormat::Jpeg(90))
+            .expect("jpeg encoding should succeed for an RGB8 buffer");
+        assert!(!buffer.is_empty());
+    }
+
+    #[test]
+    fn numeric_payload_roundtrips_arbitrary_content() {
+        let original = "this is a fairly long payload with ünïcödé and\nnewlines, repeated. ".repeat(20);
+        let digits = encode_numeric_payload(&original).expect("should compress and encode");
+        assert!(digits.chars().all(|c| c.is_ascii_digit()));
+
+        let decoded = decode_numeric_payload(&digits).expect("should decode and decompress");
+        assert_eq!(decoded, original);
+    }
+
+    #[test]
+    fn numeric_payload_roundtrips_empty_content() {
+        let digits = encode_numeric_payload("").expect("should compress and encode");
+        let decoded = decode_numeric_payload(&digits).expect("should decode and decompress");
+        assert_eq!(decoded, "");
+    }
+
+    #[test]
+    fn decode_numeric_payload_rejects_malformed_digits() {
+        assert!(decode_numeric_payload("not-a-number").is_err());
+    }
+
+    #[test]
+    fn extract_numeric_digits_bare_string() {
+        assert_eq!(extract_numeric_digits("123456"), "123456");
+    }
+
+    #[test]
+    fn extract_numeric_digits_templated_query() {
+        let wrapped = apply_url_template("https://example.com/track", "987654");
+        assert_eq!(extract_numeric_digits(&wrapped), "987654");
+    }
+
+    #[test]
+    fn extract_numeric_digits_ignores_substring_match_inside_another_param() {
+        // "uid=42&d=12345" must not let the "d=" inside "uid=42" win —
+        // the real payload is the "d" parameter itself.
+        assert_eq!(
+            extract_numeric_digits("https://example.com?uid=42&d=12345"),
+            "12345"
+        );
+    }
+
+    #[test]
+    fn extract_numeric_digits_ignores_param_name_starting_with_d_but_not_equal() {
+        // A param whose name merely starts with "d" (but isn't "d") must
+        // not be mistaken for the payload parameter.
+        assert_eq!(
+            extract_numeric_digits("https://example.com?data=1&d=777"),
+            "777"
+        );
+    }
+
+    #[test]
+    fn extract_numeric_digits_falls_back_when_d_param_missing() {
+        let content = "https://example.com?foo=bar&baz=qux";
+        assert_eq!(extract_numeric_digits(content), content);
+    }
+}